@@ -0,0 +1,5 @@
+#[macro_use]
+extern crate lazy_static;
+
+pub mod memory;
+pub mod space;
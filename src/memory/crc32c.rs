@@ -0,0 +1,87 @@
+//! Software CRC32C (Castagnoli) checksum, used for region integrity checks.
+
+const POLY: u32 = 0x82f6_3b78; // reflected 0x1edc6f41
+
+pub fn crc32c(data: &[u8]) -> u32 {
+    let mut state = Crc32cState::new();
+    state.update(data);
+    state.finish()
+}
+
+/// An incremental crc32c accumulator, so a checksum can be folded over a
+/// region's contents span by span (with zero-filled gaps folded in via
+/// [`Crc32cState::update_zeros`]) instead of requiring the whole input to be
+/// materialized in memory at once.
+pub struct Crc32cState(u32);
+
+impl Crc32cState {
+    pub fn new() -> Crc32cState {
+        Crc32cState(!0u32)
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        let mut crc = self.0;
+        for &byte in data {
+            crc ^= byte as u32;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ POLY } else { crc >> 1 };
+            }
+        }
+        self.0 = crc;
+    }
+
+    /// Folds in `len` zero bytes without allocating a `len`-sized buffer, for
+    /// skipping over the zero-filled gaps between a region's dumped spans.
+    pub fn update_zeros(&mut self, mut len: u64) {
+        const ZERO_CHUNK: [u8; 4096] = [0u8; 4096];
+        while len > 0 {
+            let chunk = std::cmp::min(len, ZERO_CHUNK.len() as u64) as usize;
+            self.update(&ZERO_CHUNK[..chunk]);
+            len -= chunk as u64;
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+impl Default for Crc32cState {
+    fn default() -> Crc32cState {
+        Crc32cState::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_known_test_vector() {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+    }
+
+    #[test]
+    fn incremental_state_matches_one_shot_crc() {
+        let mut state = Crc32cState::new();
+        state.update(b"123");
+        state.update(b"456");
+        state.update(b"789");
+        assert_eq!(state.finish(), crc32c(b"123456789"));
+    }
+
+    #[test]
+    fn update_zeros_matches_explicit_zero_buffer() {
+        let mut incremental = Crc32cState::new();
+        incremental.update(b"abc");
+        incremental.update_zeros(6000);
+        incremental.update(b"xyz");
+
+        let mut explicit = Vec::new();
+        explicit.extend_from_slice(b"abc");
+        explicit.resize(explicit.len() + 6000, 0u8);
+        explicit.extend_from_slice(b"xyz");
+
+        assert_eq!(incremental.finish(), crc32c(&explicit));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod region;
+pub mod sparse;
+pub mod cow;
+pub mod crc32c;
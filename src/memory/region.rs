@@ -0,0 +1,275 @@
+//! Backing storage and typed access for address-space regions.
+
+use std::sync::Mutex;
+
+/// A half-open `[start, end)` address interval.
+///
+/// All arithmetic saturates instead of wrapping, so a region touching the
+/// top of the address space (e.g. one ending at `u64::MAX`) is handled
+/// correctly instead of silently overflowing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRange {
+    start: u64,
+    len: u64,
+}
+
+impl MemoryRange {
+    pub fn new(start: u64, len: u64) -> MemoryRange {
+        MemoryRange { start, len }
+    }
+
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    pub fn end(&self) -> u64 {
+        self.start.saturating_add(self.len)
+    }
+
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    //widened to u128 so a range ending exactly at (or past) u64::MAX compares
+    //correctly instead of having its true end silently clamped away
+    fn end_u128(&self) -> u128 {
+        self.start as u128 + self.len as u128
+    }
+
+    pub fn contains(&self, addr: u64) -> bool {
+        !self.is_empty() && addr >= self.start && (addr as u128) < self.end_u128()
+    }
+
+    pub fn overlaps(&self, other: &MemoryRange) -> bool {
+        !self.is_empty() && !other.is_empty() && (self.start as u128) < other.end_u128() && (other.start as u128) < self.end_u128()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RegionInfo {
+    range: MemoryRange,
+}
+
+impl RegionInfo {
+    pub fn new(base: u64, size: u64) -> RegionInfo {
+        RegionInfo { range: MemoryRange::new(base, size) }
+    }
+
+    pub fn base(&self) -> u64 {
+        self.range.start()
+    }
+
+    pub fn size(&self) -> u64 {
+        self.range.len()
+    }
+
+    pub fn range(&self) -> MemoryRange {
+        self.range
+    }
+}
+
+/// The storage behind a [`Region`]. Different backends (plain heap, sparse,
+/// copy-on-write, ...) all speak in region-relative byte offsets, leaving
+/// [`Region`] to own the address-space bookkeeping (`info`) and dispatch.
+pub(crate) trait Backing: Send + Sync {
+    fn kind(&self) -> &'static str;
+    fn read_bytes(&self, offset: u64, data: &mut [u8]);
+    fn write_bytes(&self, offset: u64, data: &[u8]);
+
+    /// Bytes of physical storage actually allocated by this backend.
+    /// Dense backends (e.g. plain heap RAM) report their full size; sparse
+    /// or copy-on-write backends report only what has been touched.
+    fn allocated_bytes(&self) -> u64 {
+        0
+    }
+
+    /// Region-relative `[start, end)` ranges that currently have backing
+    /// storage allocated. Empty for backends that don't track this.
+    fn mapped_ranges(&self) -> Vec<MemoryRange> {
+        Vec::new()
+    }
+}
+
+struct Heap(Mutex<Vec<u8>>);
+
+impl Backing for Heap {
+    fn kind(&self) -> &'static str {
+        "ram"
+    }
+
+    fn read_bytes(&self, offset: u64, data: &mut [u8]) {
+        let offset = offset as usize;
+        data.copy_from_slice(&self.0.lock().unwrap()[offset..offset + data.len()]);
+    }
+
+    fn write_bytes(&self, offset: u64, data: &[u8]) {
+        let offset = offset as usize;
+        self.0.lock().unwrap()[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    fn allocated_bytes(&self) -> u64 {
+        self.0.lock().unwrap().len() as u64
+    }
+}
+
+/// A mapped window of the address space, backed by a pluggable [`Backing`]
+/// implementation (plain RAM by default; see the `sparse` and `cow` modules
+/// for thin-provisioned and copy-on-write alternatives).
+pub struct Region {
+    pub info: RegionInfo,
+    backend: Box<dyn Backing>,
+}
+
+impl Region {
+    pub fn new(base: u64, size: u64) -> Region {
+        Region::with_backend(RegionInfo::new(base, size), Box::new(Heap(Mutex::new(vec![0u8; size as usize]))))
+    }
+
+    pub(crate) fn with_backend(info: RegionInfo, backend: Box<dyn Backing>) -> Region {
+        Region { info, backend }
+    }
+
+    pub fn get_type(&self) -> &'static str {
+        self.backend.kind()
+    }
+
+    /// Bytes of physical storage this region has actually allocated. Equal
+    /// to the region's full size unless the backend is sparse or
+    /// copy-on-write.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.backend.allocated_bytes()
+    }
+
+    /// Absolute `[start, end)` ranges that currently have backing storage
+    /// allocated, for backends that track a sparse footprint. Backends
+    /// report block-sized ranges that can run past the region's own end
+    /// when its size isn't a multiple of the block size, so each range is
+    /// clamped to the region's bounds here.
+    pub fn mapped_ranges(&self) -> Vec<MemoryRange> {
+        let base = self.info.base();
+        let limit = self.info.range().end();
+        self.backend.mapped_ranges().into_iter()
+            .filter_map(|r| {
+                let start = std::cmp::min(base.saturating_add(r.start()), limit);
+                let end = std::cmp::min(start.saturating_add(r.len()), limit);
+                let clamped = MemoryRange::new(start, end.saturating_sub(start));
+                if clamped.is_empty() { None } else { Some(clamped) }
+            })
+            .collect()
+    }
+
+    fn offset(&self, addr: u64) -> u64 {
+        addr - self.info.base()
+    }
+}
+
+pub trait U8Access {
+    fn write(&self, addr: u64, data: u8);
+    fn read(&self, addr: u64) -> u8;
+}
+
+pub trait U16Access {
+    fn write(&self, addr: u64, data: u16);
+    fn read(&self, addr: u64) -> u16;
+}
+
+pub trait U32Access {
+    fn write(&self, addr: u64, data: u32);
+    fn read(&self, addr: u64) -> u32;
+}
+
+pub trait U64Access {
+    fn write(&self, addr: u64, data: u64);
+    fn read(&self, addr: u64) -> u64;
+}
+
+pub trait BytesAccess {
+    fn write(&self, addr: u64, data: &[u8]);
+    fn read(&self, addr: u64, data: &mut [u8]);
+}
+
+impl U8Access for Region {
+    fn write(&self, addr: u64, data: u8) {
+        self.backend.write_bytes(self.offset(addr), &[data]);
+    }
+
+    fn read(&self, addr: u64) -> u8 {
+        let mut byte = [0u8; 1];
+        self.backend.read_bytes(self.offset(addr), &mut byte);
+        byte[0]
+    }
+}
+
+macro_rules! impl_int_access {
+    ($trait_name:ident, $ty:ty) => {
+        impl $trait_name for Region {
+            fn write(&self, addr: u64, data: $ty) {
+                self.backend.write_bytes(self.offset(addr), &data.to_le_bytes());
+            }
+
+            fn read(&self, addr: u64) -> $ty {
+                let mut bytes = [0u8; std::mem::size_of::<$ty>()];
+                self.backend.read_bytes(self.offset(addr), &mut bytes);
+                <$ty>::from_le_bytes(bytes)
+            }
+        }
+    };
+}
+
+impl_int_access!(U16Access, u16);
+impl_int_access!(U32Access, u32);
+impl_int_access!(U64Access, u64);
+
+impl BytesAccess for Region {
+    fn write(&self, addr: u64, data: &[u8]) {
+        self.backend.write_bytes(self.offset(addr), data);
+    }
+
+    fn read(&self, addr: u64, data: &mut [u8]) {
+        self.backend.read_bytes(self.offset(addr), data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_contains_and_overlaps() {
+        let a = MemoryRange::new(0x1000, 0x1000);
+        assert!(a.contains(0x1000));
+        assert!(a.contains(0x1fff));
+        assert!(!a.contains(0x2000));
+
+        let b = MemoryRange::new(0x1800, 0x100);
+        assert!(a.overlaps(&b));
+        assert!(b.overlaps(&a));
+
+        let c = MemoryRange::new(0x2000, 0x100);
+        assert!(!a.overlaps(&c));
+    }
+
+    #[test]
+    fn range_at_top_of_address_space_does_not_overflow() {
+        let top = MemoryRange::new(u64::MAX - 0xff, 0x100);
+        assert_eq!(top.end(), u64::MAX.saturating_add(1));
+        assert!(top.contains(u64::MAX));
+        assert!(!top.contains(0));
+
+        let touching = MemoryRange::new(u64::MAX, 0x10);
+        assert!(top.overlaps(&touching));
+    }
+
+    #[test]
+    fn zero_length_range_contains_nothing_and_never_overlaps() {
+        let empty = MemoryRange::new(0x1000, 0);
+        assert!(!empty.contains(0x1000));
+        let other = MemoryRange::new(0x1000, 0x1000);
+        assert!(!empty.overlaps(&other));
+        assert!(!other.overlaps(&empty));
+    }
+}
@@ -0,0 +1,130 @@
+//! Thin-provisioned region backing: physical storage is only allocated for
+//! blocks that have actually been written, so a large, mostly-empty address
+//! window (e.g. multi-gigabyte guest RAM) only pays for the pages it touches.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use crate::memory::region::{Backing, MemoryRange, Region, RegionInfo};
+
+const DEFAULT_BLOCK_SIZE: u64 = 4096;
+
+struct SparseBacking {
+    block_size: u64,
+    blocks: Mutex<BTreeMap<u64, Box<[u8]>>>,
+}
+
+impl SparseBacking {
+    fn block_index(&self, offset: u64) -> u64 {
+        offset / self.block_size
+    }
+}
+
+impl Backing for SparseBacking {
+    fn kind(&self) -> &'static str {
+        "sparse"
+    }
+
+    fn read_bytes(&self, offset: u64, data: &mut [u8]) {
+        let blocks = self.blocks.lock().unwrap();
+        let mut cursor = offset;
+        let mut remaining = data;
+        while !remaining.is_empty() {
+            let block = self.block_index(cursor);
+            let block_offset = (cursor - block * self.block_size) as usize;
+            let chunk_len = std::cmp::min(remaining.len() as u64, self.block_size - block_offset as u64) as usize;
+            let (chunk, rest) = remaining.split_at_mut(chunk_len);
+            match blocks.get(&block) {
+                Some(bytes) => chunk.copy_from_slice(&bytes[block_offset..block_offset + chunk_len]),
+                //unmapped block: zero-fill without allocating
+                None => chunk.iter_mut().for_each(|b| *b = 0),
+            }
+            cursor += chunk_len as u64;
+            remaining = rest;
+        }
+    }
+
+    fn write_bytes(&self, offset: u64, data: &[u8]) {
+        let mut blocks = self.blocks.lock().unwrap();
+        let mut cursor = offset;
+        let mut remaining = data;
+        while !remaining.is_empty() {
+            let block = self.block_index(cursor);
+            let block_offset = (cursor - block * self.block_size) as usize;
+            let chunk_len = std::cmp::min(remaining.len() as u64, self.block_size - block_offset as u64) as usize;
+            let (chunk, rest) = remaining.split_at(chunk_len);
+            let bytes = blocks.entry(block).or_insert_with(|| vec![0u8; self.block_size as usize].into_boxed_slice());
+            bytes[block_offset..block_offset + chunk_len].copy_from_slice(chunk);
+            cursor += chunk_len as u64;
+            remaining = rest;
+        }
+    }
+
+    fn allocated_bytes(&self) -> u64 {
+        self.blocks.lock().unwrap().len() as u64 * self.block_size
+    }
+
+    fn mapped_ranges(&self) -> Vec<MemoryRange> {
+        self.blocks.lock().unwrap().keys()
+            .map(|block| MemoryRange::new(block * self.block_size, self.block_size))
+            .collect()
+    }
+}
+
+impl Region {
+    /// A region spanning `[base, base + size)` that only allocates backing
+    /// storage for the `block_size`-sized blocks that have been written.
+    /// Reads of unmapped blocks return zero-fill.
+    pub fn new_sparse(base: u64, size: u64, block_size: u64) -> Region {
+        let backend = SparseBacking { block_size, blocks: Mutex::new(BTreeMap::new()) };
+        Region::with_backend(RegionInfo::new(base, size), Box::new(backend))
+    }
+
+    /// Convenience constructor using the default 4 KiB block size.
+    pub fn new_sparse_default(base: u64, size: u64) -> Region {
+        Region::new_sparse(base, size, DEFAULT_BLOCK_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::region::BytesAccess;
+
+    #[test]
+    fn unmapped_reads_are_zero_filled_and_allocate_nothing() {
+        let region = Region::new_sparse(0x1000, 0x10000, 0x1000);
+        let mut buf = [0xffu8; 16];
+        BytesAccess::read(&region, 0x1500, &mut buf);
+        assert_eq!(buf, [0u8; 16]);
+        assert_eq!(region.allocated_bytes(), 0);
+    }
+
+    #[test]
+    fn write_allocates_only_the_touched_block() {
+        let region = Region::new_sparse(0, 0x10000, 0x1000);
+        BytesAccess::write(&region, 0x2010, &[1, 2, 3, 4]);
+
+        let mut buf = [0u8; 4];
+        BytesAccess::read(&region, 0x2010, &mut buf);
+        assert_eq!(buf, [1, 2, 3, 4]);
+
+        let mut untouched = [0u8; 4];
+        BytesAccess::read(&region, 0x3010, &mut untouched);
+        assert_eq!(untouched, [0u8; 4]);
+        assert_eq!(region.allocated_bytes(), 0x1000);
+    }
+
+    #[test]
+    fn mapped_ranges_are_clamped_when_size_is_not_a_block_multiple() {
+        let region = Region::new_sparse(0x10000, 0x10500, 0x1000);
+        // 0x20080 falls in the region's last block, which only has 0x500
+        // valid bytes since the region's size isn't a multiple of 0x1000
+        BytesAccess::write(&region, 0x20080, &[1, 2, 3, 4]);
+
+        let mapped = region.mapped_ranges();
+        assert_eq!(mapped.len(), 1);
+        assert_eq!(mapped[0].start(), 0x20000);
+        assert_eq!(mapped[0].len(), 0x500);
+        assert_eq!(mapped[0].end(), region.info.range().end());
+    }
+}
@@ -1,18 +1,32 @@
 use std::collections::{HashMap, BTreeMap};
 use std::sync::{Arc, Mutex};
 use crate::memory::region::{Region, U8Access, U16Access, U32Access, U64Access, BytesAccess};
+use crate::memory::crc32c::{crc32c, Crc32cState};
 use std::ops::Deref;
 use std::fmt::{Display, Formatter};
 use std::fmt;
-use std::ops::Bound::{Included, Unbounded};
+use std::ops::Bound::{Excluded, Included, Unbounded};
+use std::io::{self, Read, Write};
+
+//block size used when digesting a region for integrity tracking, and when
+//scanning a region for zero-filled runs to skip while dumping it
+const CHECKSUM_BLOCK_SIZE: u64 = 4096;
+
+const DUMP_MAGIC: &[u8; 4] = b"SPAC";
 
 #[derive(Debug)]
 pub enum Error {
     Overlap(String, String),
     Renamed(String, String),
+    NotFound(String),
+    Io(String),
+    Corrupt(String),
 }
 
 
+//the FFI caller hands us a pointer to its own boxed Arc<Region>; we only
+//ever read it back to drop it, so the extra indirection is intentional
+#[allow(clippy::redundant_allocation)]
 struct RegionCPtr(*const Box<Arc<Region>>);
 
 unsafe impl Send for RegionCPtr {}
@@ -23,57 +37,73 @@ unsafe impl Sync for RegionCPtr {}
 //Space should be an owner of Regions
 pub struct Space {
     regions: BTreeMap<u64, (String, Arc<Region>)>,
+    //name -> base, so duplicate-name checks and lookups don't need a linear scan
+    names: HashMap<String, u64>,
     //for ffi free
     ptrs: HashMap<String, Vec<RegionCPtr>>,
+    //per-region, per-block crc32c digests for regions under integrity tracking
+    checksums: Mutex<HashMap<String, BTreeMap<u64, u32>>>,
+}
+
+impl Default for Space {
+    fn default() -> Space {
+        Space::new()
+    }
 }
 
 impl Space {
     pub fn new() -> Space {
-        Space { regions: BTreeMap::new(), ptrs: HashMap::new() }
+        Space { regions: BTreeMap::new(), names: HashMap::new(), ptrs: HashMap::new(), checksums: Mutex::new(HashMap::new()) }
     }
 
     pub fn add_region(&mut self, name: &str, region: &Arc<Region>) -> Result<Arc<Region>, Error> {
         let check = || {
-            if let Some(_) = self.regions.values().find(|(n, _)| { n == name }) {
+            if self.names.contains_key(name) {
                 return Err(Error::Renamed(name.to_string(), format!("region name {} has existed!", name)));
             }
-            if let Some(v) = self.regions.values().find(|(_, v)| {
-                region.info.base >= v.info.base && region.info.base < v.info.base + v.info.size ||
-                    region.info.base + region.info.size - 1 >= v.info.base && region.info.base + region.info.size - 1 < v.info.base + v.info.size ||
-                    v.info.base >= region.info.base && v.info.base < region.info.base + region.info.size ||
-                    v.info.base + v.info.size - 1 >= region.info.base && v.info.base + v.info.size - 1 < region.info.base + region.info.size
-            }) {
+            let range = region.info.range();
+            let base = range.start();
+            //regions is kept non-overlapping, so a candidate can only collide
+            //with the neighbor immediately below or immediately above its base
+            let lower = self.regions.range((Unbounded, Included(base))).next_back().map(|(_, v)| v);
+            let upper = self.regions.range((Excluded(base), Unbounded)).next().map(|(_, v)| v);
+            if let Some(v) = lower.into_iter().chain(upper).find(|(_, v)| range.overlaps(&v.info.range())) {
                 return Err(Error::Overlap(v.0.to_string(), format!("region [{} : {:?}] is overlapped with [{} : {:?}]!", name, region.deref().info, v.0, v.1.deref().info)));
             }
             Ok(())
         };
         check()?;
-        self.regions.insert(region.info.base, (name.to_string(), Arc::clone(region)));
+        self.names.insert(name.to_string(), region.info.base());
+        self.regions.insert(region.info.base(), (name.to_string(), Arc::clone(region)));
         Ok(Arc::clone(region))
     }
 
     pub fn delete_region(&mut self, name: &str) {
-        let res = self.regions.iter().find_map(|(k, (n, _))| { if n == name { Some(*k) } else { None } });
-        if let Some(k) = res {
+        if let Some(k) = self.names.remove(name) {
             self.regions.remove(&k);
         }
         if let Some(ps) = self.ptrs.remove(name) {
             ps.iter().for_each(|RegionCPtr(ptr)| { std::mem::drop(unsafe { (*ptr).read() }) })
         }
+        self.checksums.lock().unwrap().remove(name);
     }
 
     pub fn get_region(&self, name: &str) -> Option<Arc<Region>> {
-        if let Some(v) = self.regions.values().find_map(|(n, region)| { if n == name { Some(region) } else { None } }) {
-            Some(Arc::clone(v))
+        if let Some(v) = self.names.get(name).and_then(|base| self.regions.get(base)) {
+            Some(Arc::clone(&v.1))
         } else {
             None
         }
     }
 
     pub fn get_region_by_addr(&self, addr: u64) -> Result<Arc<Region>, u64> {
-        if let Some((_, (_, v))) = self.regions.range((Unbounded,Included(&addr))).last() {
-            if addr < v.info.base + v.info.size {
-                Ok(Arc::clone(v))
+        self.region_entry_by_addr(addr).map(|(_, region)| region)
+    }
+
+    fn region_entry_by_addr(&self, addr: u64) -> Result<(String, Arc<Region>), u64> {
+        if let Some((_, (name, v))) = self.regions.range((Unbounded,Included(&addr))).last() {
+            if v.info.range().contains(addr) {
+                Ok((name.clone(), Arc::clone(v)))
             } else {
                 Err(addr)
             }
@@ -82,9 +112,32 @@ impl Space {
         }
     }
 
+    //recomputes the crc32c of every CHECKSUM_BLOCK_SIZE block touched by
+    //[addr, addr + len), if `name` is under integrity tracking. The block
+    //grid is anchored at the region's own base (matching `digest_blocks`),
+    //not a globally-aligned grid, so a region whose base isn't a multiple
+    //of CHECKSUM_BLOCK_SIZE still gets blocks that start at/after its base.
+    fn touch_checksum(&self, name: &str, region: &Region, addr: u64, len: u64) {
+        let mut tracked = self.checksums.lock().unwrap();
+        if let Some(blocks) = tracked.get_mut(name) {
+            let base = region.info.range().start();
+            let end = region.info.range().end();
+            let mut block_base = base + (addr - base) / CHECKSUM_BLOCK_SIZE * CHECKSUM_BLOCK_SIZE;
+            while block_base < addr + len {
+                let block_len = std::cmp::min(CHECKSUM_BLOCK_SIZE, end.saturating_sub(block_base));
+                let mut buf = vec![0u8; block_len as usize];
+                BytesAccess::read(region, block_base, &mut buf);
+                blocks.insert(block_base, crc32c(&buf));
+                block_base += CHECKSUM_BLOCK_SIZE;
+            }
+        }
+    }
+
     pub fn write_u8(&self, addr: u64, data: u8) -> Result<(), u64> {
-        let region = self.get_region_by_addr(addr)?;
-        Ok(U8Access::write(region.deref(), addr, data))
+        let (name, region) = self.region_entry_by_addr(addr)?;
+        U8Access::write(region.deref(), addr, data);
+        self.touch_checksum(&name, region.deref(), addr, 1);
+        Ok(())
     }
 
     pub fn read_u8(&self, addr: u64) -> Result<u8, u64> {
@@ -93,8 +146,10 @@ impl Space {
     }
 
     pub fn write_u16(&self, addr: u64, data: u16) -> Result<(), u64> {
-        let region = self.get_region_by_addr(addr)?;
-        Ok(U16Access::write(region.deref(), addr, data))
+        let (name, region) = self.region_entry_by_addr(addr)?;
+        U16Access::write(region.deref(), addr, data);
+        self.touch_checksum(&name, region.deref(), addr, 2);
+        Ok(())
     }
 
     pub fn read_u16(&self, addr: u64) -> Result<u16, u64> {
@@ -103,8 +158,10 @@ impl Space {
     }
 
     pub fn write_u32(&self, addr: u64, data: u32) -> Result<(), u64> {
-        let region = self.get_region_by_addr(addr)?;
-        Ok(U32Access::write(region.deref(), addr, data))
+        let (name, region) = self.region_entry_by_addr(addr)?;
+        U32Access::write(region.deref(), addr, data);
+        self.touch_checksum(&name, region.deref(), addr, 4);
+        Ok(())
     }
 
     pub fn read_u32(&self, addr: u64) -> Result<u32, u64> {
@@ -113,8 +170,10 @@ impl Space {
     }
 
     pub fn write_u64(&self, addr: u64, data: u64) -> Result<(), u64> {
-        let region = self.get_region_by_addr(addr)?;
-        Ok(U64Access::write(region.deref(), addr, data))
+        let (name, region) = self.region_entry_by_addr(addr)?;
+        U64Access::write(region.deref(), addr, data);
+        self.touch_checksum(&name, region.deref(), addr, 8);
+        Ok(())
     }
 
     pub fn read_u64(&self, addr: u64) -> Result<u64, u64> {
@@ -122,27 +181,297 @@ impl Space {
         Ok(U64Access::read(region.deref(), addr))
     }
 
-    fn write_bytes(&self, addr: u64, data: &[u8]) -> Result<(), u64> {
-        let region = self.get_region_by_addr(addr)?;
-        Ok(BytesAccess::write(region.deref(), addr, data))
+    //walks the regions covering [addr, addr + data.len()) in base order, so a
+    //transfer that straddles adjacent regions is split into one access per
+    //region instead of running past the first region it finds
+    pub fn write_bytes(&self, addr: u64, data: &[u8]) -> Result<(), u64> {
+        let mut cursor = addr;
+        let mut remaining = data;
+        while !remaining.is_empty() {
+            let (name, region) = self.region_entry_by_addr(cursor).map_err(|_| cursor)?;
+            let chunk_len = std::cmp::min(remaining.len() as u64, region.info.range().end() - cursor) as usize;
+            let (chunk, rest) = remaining.split_at(chunk_len);
+            BytesAccess::write(region.deref(), cursor, chunk);
+            self.touch_checksum(&name, region.deref(), cursor, chunk_len as u64);
+            cursor += chunk_len as u64;
+            remaining = rest;
+        }
+        Ok(())
     }
 
-    fn read_bytes(&self, addr: u64, data: &mut [u8]) -> Result<(), u64> {
-        let region = self.get_region_by_addr(addr)?;
-        Ok(BytesAccess::read(region.deref(), addr, data))
+    pub fn read_bytes(&self, addr: u64, data: &mut [u8]) -> Result<(), u64> {
+        let mut cursor = addr;
+        let mut remaining = data;
+        while !remaining.is_empty() {
+            let region = self.get_region_by_addr(cursor).map_err(|_| cursor)?;
+            let chunk_len = std::cmp::min(remaining.len() as u64, region.info.range().end() - cursor) as usize;
+            let (chunk, rest) = remaining.split_at_mut(chunk_len);
+            BytesAccess::read(region.deref(), cursor, chunk);
+            cursor += chunk_len as u64;
+            remaining = rest;
+        }
+        Ok(())
     }
 
     pub fn clean(&mut self, name: &str, ptr: *const Box<Arc<Region>>) {
         self.ptrs.entry(String::from(name)).or_insert(vec![])
             .push(RegionCPtr(ptr))
     }
+
+    /// Start digesting `name` in `CHECKSUM_BLOCK_SIZE` blocks so later writes
+    /// through `write_u*`/`write_bytes` keep the digest up to date and
+    /// `verify`/`checksum_region` have something to compare against.
+    pub fn track_integrity(&self, name: &str) -> Result<(), Error> {
+        let region = self.get_region(name).ok_or_else(|| Error::NotFound(name.to_string()))?;
+        let blocks = Self::digest_blocks(region.deref());
+        self.checksums.lock().unwrap().insert(name.to_string(), blocks);
+        Ok(())
+    }
+
+    fn digest_blocks(region: &Region) -> BTreeMap<u64, u32> {
+        let range = region.info.range();
+        let mut blocks = BTreeMap::new();
+        let mut block_base = range.start();
+        while block_base < range.end() {
+            let block_len = std::cmp::min(CHECKSUM_BLOCK_SIZE, range.end() - block_base);
+            let mut buf = vec![0u8; block_len as usize];
+            BytesAccess::read(region, block_base, &mut buf);
+            blocks.insert(block_base, crc32c(&buf));
+            block_base += CHECKSUM_BLOCK_SIZE;
+        }
+        blocks
+    }
+
+    /// Recomputes the digest of every tracked block of `name` and reports
+    /// the base address of each block whose content no longer matches its
+    /// recorded digest (taken by `track_integrity` and refreshed by writes
+    /// through this `Space`).
+    pub fn verify(&self, name: &str) -> Result<(), Vec<u64>> {
+        let region = match self.get_region(name) {
+            Some(region) => region,
+            None => return Ok(()),
+        };
+        let tracked = self.checksums.lock().unwrap();
+        let blocks = match tracked.get(name) {
+            Some(blocks) => blocks,
+            None => return Ok(()),
+        };
+        let range = region.info.range();
+        let mismatches: Vec<u64> = blocks.iter().filter_map(|(&block_base, &expected)| {
+            let block_len = std::cmp::min(CHECKSUM_BLOCK_SIZE, range.end() - block_base);
+            let mut buf = vec![0u8; block_len as usize];
+            BytesAccess::read(region.deref(), block_base, &mut buf);
+            if crc32c(&buf) == expected { None } else { Some(block_base) }
+        }).collect();
+        if mismatches.is_empty() { Ok(()) } else { Err(mismatches) }
+    }
+
+    /// A single crc32c fingerprint of the entire region `name`, independent
+    /// of integrity tracking, so two `Space`s can be compared cheaply.
+    pub fn checksum_region(&self, name: &str) -> Option<u32> {
+        let region = self.get_region(name)?;
+        let spans = Self::dump_spans(region.deref());
+        Some(Self::spans_crc32c(region.deref(), &spans))
+    }
+
+    /// A cheap checkpoint of the whole address map: every region in the
+    /// returned `Space` is a copy-on-write wrapper sharing `self`'s current
+    /// regions as its base, so writes through the snapshot never disturb
+    /// `self`. Take the snapshot, then treat it as your working copy going
+    /// forward — `self`'s regions remain the live base the snapshot reads
+    /// through until overridden, so further writes to `self` itself are
+    /// still visible through the snapshot.
+    pub fn snapshot(&self) -> Space {
+        let mut copy = Space::new();
+        for (base, (name, region)) in self.regions.iter() {
+            let wrapped = Arc::new(Region::snapshot(region));
+            copy.names.insert(name.clone(), *base);
+            copy.regions.insert(*base, (name.clone(), wrapped));
+        }
+        copy
+    }
+
+    //the byte ranges of `region` worth dumping: the backend's own mapped
+    //footprint if it tracks one that's a faithful content footprint
+    //(sparse; CoW deliberately doesn't implement mapped_ranges, since its
+    //override set omits base-backed content), otherwise every
+    //CHECKSUM_BLOCK_SIZE block that isn't all-zero
+    fn dump_spans(region: &Region) -> Vec<(u64, u64)> {
+        let mapped = region.mapped_ranges();
+        if !mapped.is_empty() {
+            return mapped.iter().map(|r| (r.start(), r.len())).collect();
+        }
+        let range = region.info.range();
+        let mut spans = Vec::new();
+        let mut block_base = range.start();
+        while block_base < range.end() {
+            let block_len = std::cmp::min(CHECKSUM_BLOCK_SIZE, range.end() - block_base);
+            let mut buf = vec![0u8; block_len as usize];
+            BytesAccess::read(region, block_base, &mut buf);
+            if buf.iter().any(|&b| b != 0) {
+                spans.push((block_base, block_len));
+            }
+            block_base += CHECKSUM_BLOCK_SIZE;
+        }
+        spans
+    }
+
+    //folds a region's dumped spans (plus the zero-filled gaps between and
+    //around them) into a crc32c without ever materializing the whole
+    //region, so fingerprinting a large thin-provisioned region stays cheap
+    fn spans_crc32c(region: &Region, spans: &[(u64, u64)]) -> u32 {
+        let range = region.info.range();
+        let mut crc = Crc32cState::new();
+        let mut cursor = range.start();
+        for &(offset, len) in spans {
+            if offset > cursor {
+                crc.update_zeros(offset - cursor);
+            }
+            let mut buf = vec![0u8; len as usize];
+            BytesAccess::read(region, offset, &mut buf);
+            crc.update(&buf);
+            cursor = offset + len;
+        }
+        if range.end() > cursor {
+            crc.update_zeros(range.end() - cursor);
+        }
+        crc.finish()
+    }
+
+    /// Serializes the full region map and its contents to `writer` in a
+    /// compact, self-describing stream: per region, name/type/base/size,
+    /// then only the spans worth keeping (zero-filled stretches are
+    /// omitted and implied on restore), then a trailing crc32c.
+    pub fn dump<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(DUMP_MAGIC)?;
+        write_u32(writer, self.regions.len() as u32)?;
+        for (base, (name, region)) in self.regions.iter() {
+            write_framed(writer, name.as_bytes())?;
+            write_framed(writer, region.get_type().as_bytes())?;
+            write_u64(writer, *base)?;
+            write_u64(writer, region.info.size())?;
+
+            let spans = Self::dump_spans(region);
+            write_u32(writer, spans.len() as u32)?;
+            let mut crc = Crc32cState::new();
+            let mut cursor = region.info.range().start();
+            for (offset, len) in &spans {
+                write_u64(writer, *offset)?;
+                write_u64(writer, *len)?;
+                let mut buf = vec![0u8; *len as usize];
+                BytesAccess::read(region.deref(), *offset, &mut buf);
+                writer.write_all(&buf)?;
+
+                if *offset > cursor {
+                    crc.update_zeros(*offset - cursor);
+                }
+                crc.update(&buf);
+                cursor = *offset + *len;
+            }
+            let end = region.info.range().end();
+            if end > cursor {
+                crc.update_zeros(end - cursor);
+            }
+            write_u32(writer, crc.finish())?;
+        }
+        Ok(())
+    }
+
+    /// Reloads a `Space` previously written by [`Space::dump`]. Restored
+    /// regions are always plain RAM (the dump only preserves contents, not
+    /// the original backend), with gaps between dumped spans zero-filled.
+    pub fn restore<R: Read>(reader: &mut R) -> Result<Space, Error> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic).map_err(io_error)?;
+        if &magic != DUMP_MAGIC {
+            return Err(Error::Corrupt("not a space dump (bad magic)".to_string()));
+        }
+
+        let region_count = read_u32(reader).map_err(io_error)?;
+        let mut space = Space::new();
+        for _ in 0..region_count {
+            let name = String::from_utf8(read_framed(reader).map_err(io_error)?)
+                .map_err(|_| Error::Corrupt("region name is not valid utf8".to_string()))?;
+            let _type_name = read_framed(reader).map_err(io_error)?;
+            let base = read_u64(reader).map_err(io_error)?;
+            let size = read_u64(reader).map_err(io_error)?;
+
+            let region = Region::new(base, size);
+            let span_count = read_u32(reader).map_err(io_error)?;
+            let mut crc = Crc32cState::new();
+            let mut cursor = region.info.range().start();
+            for _ in 0..span_count {
+                let offset = read_u64(reader).map_err(io_error)?;
+                let len = read_u64(reader).map_err(io_error)?;
+                let mut buf = vec![0u8; len as usize];
+                reader.read_exact(&mut buf).map_err(io_error)?;
+                BytesAccess::write(&region, offset, &buf);
+
+                if offset > cursor {
+                    crc.update_zeros(offset - cursor);
+                }
+                crc.update(&buf);
+                cursor = offset + len;
+            }
+            let end = region.info.range().end();
+            if end > cursor {
+                crc.update_zeros(end - cursor);
+            }
+
+            let expected_crc = read_u32(reader).map_err(io_error)?;
+            if crc.finish() != expected_crc {
+                return Err(Error::Corrupt(format!("checksum mismatch restoring region {}", name)));
+            }
+
+            space.add_region(&name, &Arc::new(region))
+                .map_err(|_| Error::Corrupt(format!("region {} overlaps while restoring", name)))?;
+        }
+        Ok(space)
+    }
+}
+
+fn io_error(e: io::Error) -> Error {
+    Error::Io(e.to_string())
+}
+
+fn write_u32<W: Write>(writer: &mut W, v: u32) -> io::Result<()> {
+    writer.write_all(&v.to_le_bytes())
+}
+
+fn write_u64<W: Write>(writer: &mut W, v: u64) -> io::Result<()> {
+    writer.write_all(&v.to_le_bytes())
+}
+
+fn write_framed<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_u32(writer, bytes.len() as u32)?;
+    writer.write_all(bytes)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_framed<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = read_u32(reader)? as usize;
+    let mut bytes = vec![0u8; len];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
 }
 
 impl Display for Space {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         writeln!(f, "regions:")?;
         for (name, region) in self.regions.values() {
-            writeln!(f, "   {:<10}({:^13})  : {:#016x} -> {:#016x}", name, region.get_type(), region.info.base, region.info.base + region.info.size - 1)?;
+            let range = region.info.range();
+            writeln!(f, "   {:<10}({:^13})  : {:#016x} -> {:#016x}", name, region.get_type(), range.start(), range.end().saturating_sub(1))?;
         }
         Ok(())
     }
@@ -168,3 +497,119 @@ impl SpaceTable {
             }).clone()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dump_and_restore_round_trips_contents_across_regions() {
+        let mut space = Space::new();
+        let ram = Arc::new(Region::new(0x1000, 0x2000));
+        let sparse = Arc::new(Region::new_sparse(0x10000, 0x100000, 0x1000));
+        space.add_region("ram", &ram).unwrap();
+        space.add_region("sparse", &sparse).unwrap();
+
+        space.write_bytes(0x1500, b"hello world").unwrap();
+        space.write_u32(0x10000 + 0x1000 * 3, 0xdead_beef).unwrap();
+
+        let mut buf = Vec::new();
+        space.dump(&mut buf).unwrap();
+
+        let restored = Space::restore(&mut &buf[..]).unwrap();
+        let mut greeting = [0u8; 11];
+        restored.read_bytes(0x1500, &mut greeting).unwrap();
+        assert_eq!(&greeting, b"hello world");
+        assert_eq!(restored.read_u32(0x10000 + 0x1000 * 3).unwrap(), 0xdead_beef);
+        //an address never touched in the sparse region should restore as zero
+        assert_eq!(restored.read_u8(0x10000 + 0x1000 * 9).unwrap(), 0);
+    }
+
+    #[test]
+    fn integrity_tracking_handles_a_region_with_an_unaligned_base() {
+        let mut space = Space::new();
+        let region = Arc::new(Region::new(0x1500, 0x2000));
+        space.add_region("unaligned", &region).unwrap();
+
+        space.track_integrity("unaligned").unwrap();
+        space.write_u32(0x1500, 0xdead_beef).unwrap();
+        assert!(space.verify("unaligned").is_ok());
+        assert_eq!(space.read_u32(0x1500).unwrap(), 0xdead_beef);
+    }
+
+    #[test]
+    fn checksum_region_matches_for_a_cow_snapshot_with_identical_content() {
+        let mut space = Space::new();
+        let base = Arc::new(Region::new(0x1000, 0x2000));
+        space.add_region("base", &base).unwrap();
+        space.write_bytes(0x1000, &[1, 2, 3, 4]).unwrap();
+
+        let origin_crc = space.checksum_region("base").unwrap();
+
+        let snap = space.snapshot();
+        // overriding with the same bytes still creates an override block,
+        // but the snapshot's readable content is unchanged from the origin
+        snap.write_bytes(0x1000, &[1, 2, 3, 4]).unwrap();
+        let snapshot_crc = snap.checksum_region("base").unwrap();
+
+        assert_eq!(origin_crc, snapshot_crc);
+    }
+
+    #[test]
+    fn dump_and_restore_a_sparse_region_whose_size_is_not_a_block_multiple() {
+        let mut space = Space::new();
+        let sparse = Arc::new(Region::new_sparse(0x10000, 0x10500, 0x1000));
+        space.add_region("sparse", &sparse).unwrap();
+        // 0x20080 falls in the region's last, partial block
+        space.write_bytes(0x20080, b"tail").unwrap();
+
+        let mut buf = Vec::new();
+        space.dump(&mut buf).unwrap();
+
+        let restored = Space::restore(&mut &buf[..]).unwrap();
+        let mut tail = [0u8; 4];
+        restored.read_bytes(0x20080, &mut tail).unwrap();
+        assert_eq!(&tail, b"tail");
+    }
+
+    #[test]
+    fn dump_and_restore_a_cow_snapshot_preserves_its_non_overridden_base_backed_blocks() {
+        let mut space = Space::new();
+        let base = Arc::new(Region::new(0x1000, 0x2000));
+        space.add_region("base", &base).unwrap();
+        space.write_bytes(0x1000, &[1, 2, 3, 4]).unwrap();
+        space.write_bytes(0x2000, &[5, 6, 7, 8]).unwrap();
+
+        let snap = space.snapshot();
+        // override only the first block; the second stays base-backed
+        snap.write_bytes(0x1000, &[9, 9, 9, 9]).unwrap();
+
+        let mut buf = Vec::new();
+        snap.dump(&mut buf).unwrap();
+        let restored = Space::restore(&mut &buf[..]).unwrap();
+
+        let mut overridden = [0u8; 4];
+        restored.read_bytes(0x1000, &mut overridden).unwrap();
+        assert_eq!(overridden, [9, 9, 9, 9]);
+
+        let mut base_backed = [0u8; 4];
+        restored.read_bytes(0x2000, &mut base_backed).unwrap();
+        assert_eq!(base_backed, [5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn restore_rejects_a_truncated_or_corrupt_stream() {
+        assert!(matches!(Space::restore(&mut &b"nope"[..]), Err(Error::Corrupt(_))));
+
+        let mut space = Space::new();
+        let ram = Arc::new(Region::new(0x1000, 0x100));
+        space.add_region("ram", &ram).unwrap();
+        let mut buf = Vec::new();
+        space.dump(&mut buf).unwrap();
+        // cut off the last byte of the trailing crc32c
+        assert!(matches!(Space::restore(&mut &buf[..buf.len() - 1]), Err(Error::Io(_))));
+        let last = buf.len() - 1;
+        buf[last] ^= 0xff; // corrupt the trailing crc32c
+        assert!(matches!(Space::restore(&mut &buf[..]), Err(Error::Corrupt(_))));
+    }
+}